@@ -0,0 +1,65 @@
+use bytes::BytesMut;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// A bounded byte buffer shared between a disk-reading producer and a
+/// network-writing consumer (or vice versa). `write` blocks while the buffer
+/// is at `capacity` (notify-on-space); `read` blocks while it's empty
+/// (notify-on-data). This is what keeps a fast disk reader from running
+/// unbounded memory ahead of a slow socket.
+pub struct ByteChannel {
+    state: Mutex<State>,
+    has_space: Condvar,
+    has_data: Condvar,
+    capacity: usize,
+}
+
+struct State {
+    buf: BytesMut,
+    closed: bool,
+}
+
+impl ByteChannel {
+    pub fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(State {
+                buf: BytesMut::new(),
+                closed: false,
+            }),
+            has_space: Condvar::new(),
+            has_data: Condvar::new(),
+            capacity,
+        })
+    }
+
+    /// Blocks until there is room for all of `data`, then appends it.
+    pub fn write(&self, data: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        while state.buf.len() + data.len() > self.capacity && !state.closed {
+            state = self.has_space.wait(state).unwrap();
+        }
+        state.buf.extend_from_slice(data);
+        self.has_data.notify_one();
+    }
+
+    /// Blocks until at least one byte is buffered, then drains up to `max_len`
+    /// bytes. Returns an empty vec once the channel is closed and fully drained.
+    pub fn read(&self, max_len: usize) -> Vec<u8> {
+        let mut state = self.state.lock().unwrap();
+        while state.buf.is_empty() && !state.closed {
+            state = self.has_data.wait(state).unwrap();
+        }
+        let n = state.buf.len().min(max_len);
+        let chunk = state.buf.split_to(n).to_vec();
+        self.has_space.notify_one();
+        chunk
+    }
+
+    /// Signals that no more data will be written; blocked readers drain the
+    /// remainder and then see an empty read instead of blocking forever.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        self.has_data.notify_all();
+        self.has_space.notify_all();
+    }
+}
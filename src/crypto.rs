@@ -0,0 +1,125 @@
+use crate::FileTransferError;
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use sha2::Sha256;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+const TAG_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+/// Largest single frame `recv_and_open` will allocate for. The manifest is
+/// the biggest legitimate frame on the wire (one JSON entry per file, sent in
+/// one shot) and stays well under this even for trees with hundreds of
+/// thousands of entries; everything else (acks, digests, file chunks) is
+/// orders of magnitude smaller. `len` for the manifest frame comes off an
+/// unauthenticated plaintext length prefix, so it must be bounded before the
+/// allocation below rather than trusted outright.
+const MAX_FRAME_LEN: usize = 256 * 1024 * 1024;
+
+/// Which side of the TCP connection a `SecureChannel` is on. The two sides
+/// derive distinct send/recv keys from this, so a connection used in both
+/// directions (e.g. the control stream) never seals two different messages
+/// under the same (key, nonce) pair.
+#[derive(Clone, Copy)]
+pub enum Role {
+    /// The side that called `TcpStream::connect` (the receiver, in this crate).
+    Initiator,
+    /// The side that called `TcpListener::accept` (the sender, in this crate).
+    Acceptor,
+}
+
+/// A passcode-authenticated, end-to-end encrypted pipe over an already-connected
+/// `TcpStream`. Both peers derive the same pair of AES-256-GCM keys only if they
+/// used the same passcode, so a wrong passcode surfaces as a GCM tag failure on
+/// the first frame rather than a separate handshake check.
+pub struct SecureChannel {
+    send_cipher: Aes256Gcm,
+    recv_cipher: Aes256Gcm,
+    send_counter: u64,
+    recv_counter: u64,
+}
+
+impl SecureChannel {
+    /// Exchanges ephemeral X25519 public keys over `stream`, then derives a
+    /// directional pair of session keys from the shared secret and `passcode`
+    /// via HKDF-SHA256 (salt = passcode, info = "slkrd-v1-c2s" / "slkrd-v1-s2c").
+    /// `role` picks which of the pair is this side's send key, so the same
+    /// connection can carry traffic in both directions without nonce reuse.
+    pub fn establish(stream: &mut TcpStream, passcode: &str, role: Role) -> Result<Self, FileTransferError> {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+
+        stream.write_all(public.as_bytes())?;
+        let mut peer_bytes = [0u8; 32];
+        stream.read_exact(&mut peer_bytes)?;
+        let shared_secret = secret.diffie_hellman(&PublicKey::from(peer_bytes));
+
+        let hk = Hkdf::<Sha256>::new(Some(passcode.as_bytes()), shared_secret.as_bytes());
+        let mut c2s_key = [0u8; 32];
+        let mut s2c_key = [0u8; 32];
+        hk.expand(b"slkrd-v1-c2s", &mut c2s_key)
+            .map_err(|_| FileTransferError::InvalidPasscode)?;
+        hk.expand(b"slkrd-v1-s2c", &mut s2c_key)
+            .map_err(|_| FileTransferError::InvalidPasscode)?;
+
+        let (send_key, recv_key) = match role {
+            Role::Initiator => (c2s_key, s2c_key),
+            Role::Acceptor => (s2c_key, c2s_key),
+        };
+        let send_cipher = Aes256Gcm::new_from_slice(&send_key).map_err(|_| FileTransferError::InvalidPasscode)?;
+        let recv_cipher = Aes256Gcm::new_from_slice(&recv_key).map_err(|_| FileTransferError::InvalidPasscode)?;
+
+        Ok(Self {
+            send_cipher,
+            recv_cipher,
+            send_counter: 0,
+            recv_counter: 0,
+        })
+    }
+
+    fn nonce_for(counter: u64) -> [u8; NONCE_SIZE] {
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        nonce
+    }
+
+    /// Seals `plaintext` and writes it to `stream` as `tag (16B) || ciphertext`.
+    pub fn seal_and_send(&mut self, stream: &mut TcpStream, plaintext: &[u8]) -> Result<(), FileTransferError> {
+        let nonce = Self::nonce_for(self.send_counter);
+        self.send_counter += 1;
+
+        let mut sealed = self
+            .send_cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| FileTransferError::TransferError)?;
+        let tag = sealed.split_off(sealed.len() - TAG_SIZE);
+
+        stream.write_all(&tag)?;
+        stream.write_all(&sealed)?;
+        Ok(())
+    }
+
+    /// Reads a `tag || ciphertext` frame carrying `len` bytes of plaintext and opens it.
+    /// A wrong passcode diverges the derived key, so this fails with `InvalidPasscode`.
+    pub fn recv_and_open(&mut self, stream: &mut TcpStream, len: usize) -> Result<Vec<u8>, FileTransferError> {
+        if len > MAX_FRAME_LEN {
+            return Err(FileTransferError::TransferError);
+        }
+
+        let mut tag = [0u8; TAG_SIZE];
+        stream.read_exact(&mut tag)?;
+        let mut sealed = vec![0u8; len + TAG_SIZE];
+        stream.read_exact(&mut sealed[..len])?;
+        sealed[len..].copy_from_slice(&tag);
+
+        let nonce = Self::nonce_for(self.recv_counter);
+        self.recv_counter += 1;
+
+        self.recv_cipher
+            .decrypt(Nonce::from_slice(&nonce), sealed.as_slice())
+            .map_err(|_| FileTransferError::InvalidPasscode)
+    }
+}
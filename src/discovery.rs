@@ -0,0 +1,120 @@
+use crate::FileTransferError;
+use futures_util::{pin_mut, StreamExt};
+use libmdns::{Responder, Service};
+use mdns::RecordKind;
+use pbkdf2::pbkdf2_hmac;
+use rand::RngCore;
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+const SERVICE_TYPE: &str = "_slkrd._tcp.local";
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
+const SALT_LEN: usize = 16;
+const STRETCHED_LEN: usize = 32;
+// A 6-digit passcode has only 10^6 possibilities, so a bare hash broadcast
+// over multicast is an offline-crackable oracle for the session key; PBKDF2
+// with a per-advertisement random salt and a high iteration count makes
+// brute-forcing it from a sniffed TXT record expensive instead of instant.
+const STRETCH_ITERATIONS: u32 = 200_000;
+
+/// Advertises this sender on the LAN as `_slkrd._tcp`, with TXT records
+/// carrying a random salt and PBKDF2-SHA256(passcode, salt) instead of the
+/// passcode itself. Keep the returned `Responder`/`Service` alive for as long
+/// as the advertisement should stand; dropping them withdraws it.
+pub fn advertise(port: u16, passcode: &str) -> Result<(Responder, Service), FileTransferError> {
+    let responder = Responder::new().map_err(|_| FileTransferError::ConnectionFailed)?;
+
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let stretched = stretch_passcode(passcode, &salt);
+
+    let salt_txt = format!("salt={}", hex_encode(&salt));
+    let passcode_txt = format!("passcode={}", hex_encode(&stretched));
+    let service = responder.register(
+        "_slkrd._tcp".to_string(),
+        "slkrd".to_string(),
+        port,
+        &[&salt_txt, &passcode_txt],
+    );
+    Ok((responder, service))
+}
+
+/// Browses for a `_slkrd._tcp` peer whose advertised salted, stretched
+/// passcode matches `passcode`, and returns its resolved address, or `None`
+/// if nothing matched before `DISCOVERY_TIMEOUT` elapses.
+pub fn discover(passcode: &str) -> Result<Option<SocketAddr>, FileTransferError> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|_| FileTransferError::ConnectionFailed)?;
+
+    runtime.block_on(async move {
+        let stream = mdns::discover::all(SERVICE_TYPE, DISCOVERY_TIMEOUT)
+            .map_err(|_| FileTransferError::ConnectionFailed)?
+            .listen();
+        pin_mut!(stream);
+
+        let deadline = tokio::time::sleep(DISCOVERY_TIMEOUT);
+        pin_mut!(deadline);
+
+        loop {
+            tokio::select! {
+                response = stream.next() => {
+                    let Some(Ok(response)) = response else { continue };
+
+                    let salt = response.records().find_map(|record| txt_field(record, "salt="));
+                    let advertised = response.records().find_map(|record| txt_field(record, "passcode="));
+                    let (Some(salt), Some(advertised)) = (salt, advertised) else { continue };
+
+                    if stretch_passcode(passcode, &salt).as_slice() != advertised.as_slice() {
+                        continue;
+                    }
+
+                    let ip = response.records().find_map(|record| match record.kind {
+                        RecordKind::A(addr) => Some(IpAddr::V4(addr)),
+                        RecordKind::AAAA(addr) => Some(IpAddr::V6(addr)),
+                        _ => None,
+                    });
+                    let port = response.records().find_map(|record| match &record.kind {
+                        RecordKind::SRV { port, .. } => Some(*port),
+                        _ => None,
+                    });
+
+                    if let (Some(ip), Some(port)) = (ip, port) {
+                        return Ok(Some(SocketAddr::new(ip, port)));
+                    }
+                }
+                _ = &mut deadline => return Ok(None),
+            }
+        }
+    })
+}
+
+/// Hex-decoded value of `record`'s TXT entry starting with `prefix` (e.g. `"salt="`), if any.
+fn txt_field(record: &mdns::Record, prefix: &str) -> Option<Vec<u8>> {
+    match &record.kind {
+        RecordKind::TXT(entries) => entries.iter().find_map(|entry| entry.strip_prefix(prefix)).and_then(hex_decode),
+        _ => None,
+    }
+}
+
+fn stretch_passcode(passcode: &str, salt: &[u8]) -> [u8; STRETCHED_LEN] {
+    let mut out = [0u8; STRETCHED_LEN];
+    pbkdf2_hmac::<Sha256>(passcode.as_bytes(), salt, STRETCH_ITERATIONS, &mut out);
+    out
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
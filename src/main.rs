@@ -1,28 +1,51 @@
+mod chan;
+mod config;
+mod crypto;
+mod discovery;
+mod error;
+mod file;
+mod manifest;
+mod network;
+mod signaling;
+mod transfer;
+
 use std::env;
-use std::fs::File;
-use std::io::{self, Read, Write, Seek, ErrorKind};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write, Seek, SeekFrom, ErrorKind};
 use std::net::{TcpListener, TcpStream};
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::process;
-use std::time::Duration;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use rand::Rng;
 use socket2::Socket;
 
+use chan::ByteChannel;
+use crypto::{Role, SecureChannel};
+use error::SlkrdError;
+use manifest::Manifest;
+
 const BUFFER_SIZE: usize = 64 * 1024; // 64KB
 const DISCOVERY_PORT: u16 = 45678;
 const PASSCODE_LENGTH: usize = 6;
 const MAX_RETRIES: u32 = 3;
 const TCP_KEEPALIVE_DURATION: Duration = Duration::from_secs(60);
+const STREAM_COUNT: usize = 4;
+const STREAM_WINDOW_CHUNKS: usize = 8;
 
 #[derive(Debug)]
-enum FileTransferError {
+pub(crate) enum FileTransferError {
     FileNotFound,
     InvalidPasscode,
     ConnectionFailed,
     TransferError,
     FileExists,
     Timeout,
+    ConnectionLost,
     IncompleteTransfer(u64, u64),
+    InvalidPath,
 }
 
 impl From<io::Error> for FileTransferError {
@@ -32,23 +55,48 @@ impl From<io::Error> for FileTransferError {
             ErrorKind::InvalidInput => FileTransferError::InvalidPasscode,
             ErrorKind::TimedOut | ErrorKind::WouldBlock => FileTransferError::Timeout,
             ErrorKind::AlreadyExists => FileTransferError::FileExists,
+            ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::UnexpectedEof => FileTransferError::ConnectionLost,
             _ => FileTransferError::TransferError,
         }
     }
 }
 
+impl From<SlkrdError> for FileTransferError {
+    fn from(error: SlkrdError) -> Self {
+        match error {
+            SlkrdError::Io(e) => FileTransferError::from(e),
+            SlkrdError::InvalidPasscode => FileTransferError::InvalidPasscode,
+            SlkrdError::Network(_) => FileTransferError::ConnectionFailed,
+            SlkrdError::WebRTC(_) | SlkrdError::TransferFailed(_) => FileTransferError::TransferError,
+        }
+    }
+}
+
 fn main() -> Result<(), FileTransferError> {
     let args: Vec<String> = env::args().collect();
 
     let result = match args.len() {
         3 => match args[1].as_str() {
             "-s" => send_file(&args[2]),
-            "-r" => receive_file(&args[2]),
+            "-r" => receive_file(&args[2], None),
+            "--relay" => run_signaling_relay(&args[2]),
+            _ => {
+                print_usage();
+                Ok(())
+            }
+        },
+        4 => match args[1].as_str() {
+            "-r" => receive_file(&args[2], Some(&args[3])),
+            "-sw" => send_file_webrtc(&args[2], &args[3]),
             _ => {
                 print_usage();
                 Ok(())
             }
         },
+        5 if args[1] == "-rw" => receive_file_webrtc(&args[2], &args[3], &args[4]),
         _ => {
             print_usage();
             Ok(())
@@ -63,11 +111,13 @@ fn main() -> Result<(), FileTransferError> {
             FileTransferError::TransferError => eprintln!("Error: Transfer failed"),
             FileTransferError::FileExists => eprintln!("Error: File already exists at destination"),
             FileTransferError::Timeout => eprintln!("Error: Connection timed out"),
+            FileTransferError::ConnectionLost => eprintln!("Error: Connection lost"),
             FileTransferError::IncompleteTransfer(received, expected) => eprintln!(
                 "Error: Incomplete transfer (received {} of {})",
                 format_size(*received),
                 format_size(*expected)
             ),
+            FileTransferError::InvalidPath => eprintln!("Error: Refusing to write outside the destination directory"),
         }
     }
 
@@ -76,8 +126,14 @@ fn main() -> Result<(), FileTransferError> {
 
 fn print_usage() {
     println!("Usage:");
-    println!("  Send file:    slkrd -s <file_path>");
-    println!("  Receive file: slkrd -r <passcode>");
+    println!("  Send file or directory: slkrd -s <path>");
+    println!("  Receive into cwd:        slkrd -r <passcode> [host]");
+    println!("  (omit [host] to find the sender on the LAN via mDNS)");
+    println!();
+    println!("  NAT-traversing transfer over WebRTC (needs a reachable signaling relay):");
+    println!("  Run a relay:  slkrd --relay <bind_addr>");
+    println!("  Send a file:  slkrd -sw <path> <signaling_addr>");
+    println!("  Receive:      slkrd -rw <passcode> <signaling_addr> <dest_path>");
     process::exit(1);
 }
 
@@ -100,48 +156,52 @@ fn validate_passcode(passcode: &str) -> Result<(), FileTransferError> {
     }
 }
 
-fn send_file(file_path: &str) -> Result<(), FileTransferError> {
-    let path = Path::new(file_path);
-    if !path.exists() {
+fn send_file(source_path: &str) -> Result<(), FileTransferError> {
+    let root = Path::new(source_path);
+    if !root.exists() {
         return Err(FileTransferError::FileNotFound);
     }
 
+    let manifest = Manifest::build(root)?;
+
     let passcode = generate_passcode();
     println!("Generated passcode: {}", passcode);
 
     let listener = TcpListener::bind(("0.0.0.0", DISCOVERY_PORT))?;
     println!("Waiting for receiver... (Port: {})", DISCOVERY_PORT);
+    println!("Sending {} file(s) over {} parallel streams", manifest.entries.len(), STREAM_COUNT);
 
-    let mut file = File::open(file_path)?;
-    let file_size = file.metadata()?.len();
-    let filename = path.file_name().unwrap().to_string_lossy().to_string();
+    // Kept alive for the rest of this function so the LAN advertisement stands
+    // until we've served (or given up on) a receiver.
+    let _mdns = discovery::advertise(DISCOVERY_PORT, &passcode)?;
 
     let mut retries = 0;
     while retries < MAX_RETRIES {
-        match listener.accept() {
-            Ok((mut stream, _)) => {
-                println!("Receiver connected. Validating passcode...");
-                
-                // Receive passcode from client
-                let mut received_passcode = [0u8; PASSCODE_LENGTH];
-                stream.read_exact(&mut received_passcode)?;
-                
-                if passcode.as_bytes() != &received_passcode {
-                    println!("Invalid passcode received. Waiting for new connection...");
-                    continue;
-                }
+        match accept_stream_group(&listener, STREAM_COUNT) {
+            Ok(mut streams) => {
+                println!("Receiver connected. Establishing secure channels...");
 
-                // Send filename
-                stream.write_all(filename.len().to_le_bytes().as_ref())?;
-                stream.write_all(filename.as_bytes())?;
+                let mut channels = establish_channels(&mut streams, &passcode, Role::Acceptor)?;
+
+                // The manifest is the first sealed frame on the control channel; a
+                // receiver with the wrong passcode will fail to open it instead of
+                // us comparing bytes here.
+                let manifest_bytes = manifest.to_bytes()?;
+                streams[0].write_all(&(manifest_bytes.len() as u64).to_le_bytes())?;
+                channels[0].seal_and_send(&mut streams[0], &manifest_bytes)?;
 
                 println!("Starting file transfer...");
-                match transfer_file(&mut stream, &mut file, file_size) {
+                match send_manifest_entries(&mut streams, &mut channels, root, &manifest) {
                     Ok(()) => return Ok(()),
-                    Err(FileTransferError::Timeout) => {
-                        eprintln!("Transfer timed out, retrying... ({}/{})", retries + 1, MAX_RETRIES);
+                    Err(e @ (FileTransferError::Timeout | FileTransferError::ConnectionLost)) => {
+                        eprintln!("Transfer interrupted, retrying... ({}/{})", retries + 1, MAX_RETRIES);
                         retries += 1;
-                        file.seek(std::io::SeekFrom::Start(0))?;
+                        // No need to rewind: the receiver reports which partitions of
+                        // each file it already verified, so data from a prior attempt
+                        // is skipped cheaply instead of re-sent.
+                        if retries >= MAX_RETRIES {
+                            return Err(e);
+                        }
                     }
                     Err(e) => return Err(e),
                 }
@@ -159,101 +219,654 @@ fn send_file(file_path: &str) -> Result<(), FileTransferError> {
     Err(FileTransferError::Timeout)
 }
 
-fn receive_file(passcode: &str) -> Result<(), FileTransferError> {
+/// Accepts `count` connections from the same receiver and sorts them by the
+/// stream index each one announces as its first byte (written by
+/// `connect_stream_group`) rather than accept order: nothing guarantees
+/// connections land in the listener's queue in the order they were opened
+/// once a SYN is lost and retransmitted, and a mispaired control stream
+/// would deadlock or mis-frame the whole session. Index 0 is the control
+/// channel (manifest, per-file handshake), the rest carry disjoint byte
+/// ranges.
+fn accept_stream_group(listener: &TcpListener, count: usize) -> io::Result<Vec<TcpStream>> {
+    let mut slots: Vec<Option<TcpStream>> = (0..count).map(|_| None).collect();
+    for _ in 0..count {
+        let (mut stream, _) = listener.accept()?;
+        let mut index = [0u8; 1];
+        stream.read_exact(&mut index)?;
+        let index = index[0] as usize;
+        if index >= count || slots[index].is_some() {
+            return Err(io::Error::new(ErrorKind::InvalidData, "bad or duplicate stream index"));
+        }
+        slots[index] = Some(stream);
+    }
+
+    slots
+        .into_iter()
+        .map(|slot| slot.ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "missing stream index")))
+        .collect()
+}
+
+/// Connects `count` times to `target`, announcing each connection's intended
+/// index as its first byte so `accept_stream_group` can pair streams up
+/// correctly regardless of the order they're accepted in.
+fn connect_stream_group(target: &ConnectTarget, count: usize) -> Result<Vec<TcpStream>, FileTransferError> {
+    (0..count)
+        .map(|index| {
+            let mut stream = target.connect()?;
+            stream.write_all(&[index as u8])?;
+            Ok(stream)
+        })
+        .collect()
+}
+
+/// Performs the X25519+HKDF handshake independently on each connection in
+/// `streams`, all keyed by the same passcode. `role` must match how `streams`
+/// were obtained (`Role::Acceptor` for `accept_stream_group`, `Role::Initiator`
+/// for `connect_stream_group`) so the two directional keys line up.
+fn establish_channels(
+    streams: &mut [TcpStream],
+    passcode: &str,
+    role: Role,
+) -> Result<Vec<SecureChannel>, FileTransferError> {
+    streams.iter_mut().map(|stream| SecureChannel::establish(stream, passcode, role)).collect()
+}
+
+enum ConnectTarget {
+    Host(String),
+    Addr(std::net::SocketAddr),
+    Local,
+}
+
+impl ConnectTarget {
+    fn connect(&self) -> io::Result<TcpStream> {
+        match self {
+            ConnectTarget::Host(host) => TcpStream::connect((host.as_str(), DISCOVERY_PORT)),
+            ConnectTarget::Addr(addr) => TcpStream::connect(addr),
+            ConnectTarget::Local => TcpStream::connect(("localhost", DISCOVERY_PORT))
+                .or_else(|_| TcpStream::connect(("127.0.0.1", DISCOVERY_PORT))),
+        }
+    }
+}
+
+/// Resolves a sender to connect to: an explicit `host` if given, otherwise the
+/// LAN peer discovered via mDNS advertising `passcode`'s hash, falling back to
+/// localhost for same-machine testing if mDNS finds nothing.
+fn resolve_sender(passcode: &str, host: Option<&str>) -> Result<ConnectTarget, FileTransferError> {
+    if let Some(host) = host {
+        println!("Connecting to {}...", host);
+        return Ok(ConnectTarget::Host(host.to_string()));
+    }
+
+    println!("Discovering sender via mDNS...");
+    if let Some(addr) = discovery::discover(passcode)? {
+        println!("Found sender at {}", addr);
+        return Ok(ConnectTarget::Addr(addr));
+    }
+
+    println!("No mDNS sender found, falling back to localhost...");
+    Ok(ConnectTarget::Local)
+}
+
+fn send_manifest_entries(
+    streams: &mut [TcpStream],
+    channels: &mut [SecureChannel],
+    root: &Path,
+    manifest: &Manifest,
+) -> Result<(), FileTransferError> {
+    for entry in &manifest.entries {
+        if entry.kind == manifest::EntryKind::Directory {
+            continue; // nothing to stream; the receiver recreates it from the manifest alone
+        }
+        let path = if root.is_dir() { root.join(&entry.relative_path) } else { root.to_path_buf() };
+        transfer_file(streams, channels, &path, entry.size)?;
+    }
+    Ok(())
+}
+
+fn receive_file(passcode: &str, host: Option<&str>) -> Result<(), FileTransferError> {
     validate_passcode(passcode)?;
+    let target = resolve_sender(passcode, host)?;
+
+    let mut retries = 0;
+    loop {
+        match receive_session(&target, passcode) {
+            Ok(()) => return Ok(()),
+            Err(e @ (FileTransferError::Timeout | FileTransferError::ConnectionLost)) => {
+                retries += 1;
+                if retries >= MAX_RETRIES {
+                    return Err(e);
+                }
+                eprintln!("Connection dropped, reconnecting... ({}/{})", retries, MAX_RETRIES);
+                // No need to rewind: each file's partitions that were already
+                // received and verified are tracked in a progress sidecar, so
+                // reconnecting and re-requesting from entry 0 skips them cheaply.
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// One connect-handshake-receive attempt against `target`. Separated from
+/// `receive_file` so a dropped connection can reconnect and retry the whole
+/// session without re-downloading data already verified on a prior attempt.
+fn receive_session(target: &ConnectTarget, passcode: &str) -> Result<(), FileTransferError> {
+    let mut streams = connect_stream_group(target, STREAM_COUNT)?;
+
+    println!("Establishing secure channels...");
+    let mut channels = establish_channels(&mut streams, passcode, Role::Initiator)?;
+
+    let mut manifest_len = [0u8; 8];
+    streams[0].read_exact(&mut manifest_len)?;
+    let manifest_len = u64::from_le_bytes(manifest_len) as usize;
 
-    println!("Connecting to sender...");
-    let mut stream = TcpStream::connect(("localhost", DISCOVERY_PORT))
-        .or_else(|_| TcpStream::connect(("127.0.0.1", DISCOVERY_PORT)))?;
+    let manifest_bytes = channels[0].recv_and_open(&mut streams[0], manifest_len)?;
+    let manifest = Manifest::from_bytes(&manifest_bytes)?;
 
-    // Send passcode
-    stream.write_all(passcode.as_bytes())?;
+    println!("Receiving {} file(s) over {} parallel streams", manifest.entries.len(), STREAM_COUNT);
 
-    // Receive filename
-    let mut filename_len = [0u8; 8];
-    stream.read_exact(&mut filename_len)?;
-    let filename_len = usize::from_le_bytes(filename_len);
-    
-    let mut filename_bytes = vec![0u8; filename_len];
-    stream.read_exact(&mut filename_bytes)?;
-    let filename = String::from_utf8_lossy(&filename_bytes).to_string();
+    let destination_root = Path::new(".");
+    for entry in &manifest.entries {
+        let dest = manifest::sanitize_destination(destination_root, &entry.relative_path)?;
+        match entry.kind {
+            manifest::EntryKind::Directory => {
+                std::fs::create_dir_all(&dest)?;
+                manifest::apply_mode(&dest, entry.mode)?;
+            }
+            manifest::EntryKind::File => {
+                if let Some(parent) = dest.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                receive_and_save_file(&mut streams, &mut channels, &dest)?;
+                manifest::apply_mode(&dest, entry.mode)?;
+            }
+        }
+    }
 
-    receive_and_save_file(&mut stream, &filename)
+    // Only now, with every file in the manifest verified, are the per-file
+    // progress sidecars safe to drop: a reconnect mid-session re-enters
+    // `receive_and_save_file` for entries completed on an earlier attempt
+    // too, and that needs each one's sidecar intact to recognize its
+    // destination as already-finished rather than an unrelated pre-existing
+    // file.
+    for entry in &manifest.entries {
+        if entry.kind == manifest::EntryKind::File {
+            let dest = manifest::sanitize_destination(destination_root, &entry.relative_path)?;
+            clear_progress_sidecar(&dest);
+        }
+    }
+
+    Ok(())
 }
 
-fn transfer_file(stream: &mut TcpStream, file: &mut File, file_size: u64) -> Result<(), FileTransferError> {
-    configure_tcp_stream(stream)?;
+/// Runs the UDP signaling relay that WebRTC senders and receivers use to
+/// exchange SDP offers/answers/ICE candidates, keyed by passcode. Typically
+/// hosted on a machine reachable by both peers (unlike the peers themselves,
+/// which may both be behind NAT).
+fn run_signaling_relay(bind_addr: &str) -> Result<(), FileTransferError> {
+    println!("Starting signaling relay on {}...", bind_addr);
+    let runtime = tokio::runtime::Runtime::new().map_err(|_| FileTransferError::ConnectionFailed)?;
+    runtime
+        .block_on(async move {
+            let mut server = signaling::SignalingServer::new(bind_addr).await?;
+            server.run().await
+        })
+        .map_err(FileTransferError::from)
+}
 
-    stream.write_all(&file_size.to_le_bytes())?;
+/// Sends a single file over a WebRTC data channel, NAT traversal and all,
+/// instead of the direct-TCP path `send_file` uses. `signaling_addr` is the
+/// relay both peers rendezvous through.
+fn send_file_webrtc(source_path: &str, signaling_addr: &str) -> Result<(), FileTransferError> {
+    let root = Path::new(source_path);
+    if !root.is_file() {
+        return Err(FileTransferError::FileNotFound);
+    }
+    let total_size = std::fs::metadata(root)?.len();
+    let addr: std::net::SocketAddr = signaling_addr
+        .parse()
+        .map_err(|_| FileTransferError::ConnectionFailed)?;
 
-    let mut buffer = vec![0; BUFFER_SIZE];
-    let mut transferred = 0;
-    let start_time = std::time::Instant::now();
-    let mut last_update = start_time;
+    let passcode = generate_passcode();
+    println!("Generated passcode: {}", passcode);
+    println!("Rendezvousing through signaling relay at {}...", signaling_addr);
 
-    println!("Starting transfer of {}", format_size(file_size));
+    let config = config::Config::default();
+    let path = root.to_path_buf();
 
-    while transferred < file_size {
-        let n = file.read(&mut buffer)?;
-        if n == 0 { break; }
-        stream.write_all(&buffer[..n])?;
-        transferred += n as u64;
-
-        let now = std::time::Instant::now();
-        if now.duration_since(last_update).as_millis() >= 100 {
-            print_progress(transferred, file_size, start_time);
-            last_update = now;
+    let runtime = tokio::runtime::Runtime::new().map_err(|_| FileTransferError::ConnectionFailed)?;
+    runtime
+        .block_on(async move {
+            let signaling = signaling::SignalingClient::connect(addr, &passcode).await?;
+            let mut transfer = transfer::Transfer::new_sender(path, config.chunk_size, total_size, &config).await?;
+            transfer.run_sender(&signaling).await
+        })
+        .map_err(FileTransferError::from)
+}
+
+/// Receives a single file over a WebRTC data channel into `dest_path`. The
+/// WebRTC path negotiates no manifest, so (unlike `receive_file`) the
+/// destination is given explicitly rather than derived from a sender-supplied
+/// relative path.
+fn receive_file_webrtc(passcode: &str, signaling_addr: &str, dest_path: &str) -> Result<(), FileTransferError> {
+    validate_passcode(passcode)?;
+    let addr: std::net::SocketAddr = signaling_addr
+        .parse()
+        .map_err(|_| FileTransferError::ConnectionFailed)?;
+    println!("Waiting for sender via signaling relay at {}...", signaling_addr);
+
+    let config = config::Config::default();
+    let dest = PathBuf::from(dest_path);
+    let passcode = passcode.to_string();
+
+    let runtime = tokio::runtime::Runtime::new().map_err(|_| FileTransferError::ConnectionFailed)?;
+    runtime
+        .block_on(async move {
+            let signaling = signaling::SignalingClient::connect(addr, &passcode).await?;
+            // Total size isn't known up front on this path; `create_progress_bar`
+            // falls back to a spinner when given 0.
+            let mut transfer = transfer::Transfer::new_receiver(dest, config.chunk_size, 0, &config).await?;
+            transfer.run_receiver(&signaling).await
+        })
+        .map_err(FileTransferError::from)
+}
+
+/// Shared, per-file transfer bookkeeping handed to each parallel stream: a
+/// running byte counter for aggregate progress and whether this particular
+/// stream is the one allowed to print it.
+struct TransferProgress {
+    counter: Arc<AtomicU64>,
+    whole_file_size: u64,
+    start_time: Instant,
+    is_primary: bool,
+}
+
+impl TransferProgress {
+    fn record(&self, n: u64, last_update: &mut Instant) {
+        let done = self.counter.fetch_add(n, Ordering::Relaxed) + n;
+        if !self.is_primary {
+            return;
+        }
+        let now = Instant::now();
+        if now.duration_since(*last_update).as_millis() >= 100 {
+            print_progress(done, self.whole_file_size, self.start_time);
+            *last_update = now;
         }
     }
+}
+
+/// Path of the sidecar file recording which of a destination file's parallel
+/// partitions have already been fully received and BLAKE3-verified. Resume
+/// must not be inferred from the destination file's length: it's preallocated
+/// to its final size up front (so positioned writes can land anywhere in it),
+/// so a file's on-disk length is `file_size` the instant it's created, long
+/// before any bytes have actually arrived.
+fn progress_sidecar_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".slkrd-progress");
+    dest.with_file_name(name)
+}
+
+/// Loads which of `count` partitions are already verified, or all-false if no
+/// sidecar exists yet or it doesn't match the expected partition count.
+fn load_completed_partitions(dest: &Path, count: usize) -> Vec<bool> {
+    match std::fs::read(progress_sidecar_path(dest)) {
+        Ok(bytes) if bytes.len() == count => bytes.iter().map(|&b| b != 0).collect(),
+        _ => vec![false; count],
+    }
+}
+
+/// Persists `completed` as the sidecar for `dest`. Called up front (before any
+/// partition has actually verified) so the sidecar's mere presence marks
+/// "transfer in progress", distinguishing a dropped transfer's preallocated
+/// file from an unrelated pre-existing one -- and again every time a
+/// partition finishes, so a reconnect after that point won't re-request it.
+fn save_completed_partitions(dest: &Path, completed: &[bool]) -> io::Result<()> {
+    std::fs::write(
+        progress_sidecar_path(dest),
+        completed.iter().map(|&done| done as u8).collect::<Vec<u8>>(),
+    )
+}
+
+fn mark_partition_complete(dest: &Path, completed: &Mutex<Vec<bool>>, index: usize) -> io::Result<()> {
+    let mut completed = completed.lock().unwrap();
+    completed[index] = true;
+    save_completed_partitions(dest, &completed)
+}
+
+fn clear_progress_sidecar(dest: &Path) {
+    let _ = std::fs::remove_file(progress_sidecar_path(dest));
+}
+
+/// Splits `[start, end)` into `parts` contiguous, nearly-equal ranges.
+fn split_ranges(start: u64, end: u64, parts: usize) -> Vec<Range<u64>> {
+    let total = end.saturating_sub(start);
+    let base = total / parts as u64;
+    let extra = total % parts as u64;
+
+    let mut ranges = Vec::with_capacity(parts);
+    let mut cursor = start;
+    for i in 0..parts {
+        let size = base + if (i as u64) < extra { 1 } else { 0 };
+        ranges.push(cursor..cursor + size);
+        cursor += size;
+    }
+    ranges
+}
 
+fn transfer_file(
+    streams: &mut [TcpStream],
+    channels: &mut [SecureChannel],
+    path: &Path,
+    file_size: u64,
+) -> Result<(), FileTransferError> {
+    for stream in streams.iter() {
+        configure_tcp_stream(stream)?;
+    }
+
+    // The control channel (index 0) carries the size handshake for this file;
+    // the receiver answers with which of the `streams.len()` partitions it
+    // already holds a verified copy of from a previous interrupted attempt.
+    channels[0].seal_and_send(&mut streams[0], &file_size.to_le_bytes())?;
+    let ack = channels[0].recv_and_open(&mut streams[0], streams.len())?;
+    let completed: Vec<bool> = ack.iter().map(|&b| b != 0).collect();
+
+    let ranges = split_ranges(0, file_size, streams.len());
+    let resume_offset: u64 = completed
+        .iter()
+        .zip(&ranges)
+        .filter(|(&done, _)| done)
+        .map(|(_, range)| range.end - range.start)
+        .sum();
+
+    if resume_offset > 0 {
+        println!("Resuming {} ({} of {} already verified)", path.display(), format_size(resume_offset), format_size(file_size));
+    }
+    println!("Starting transfer of {} ({})", path.display(), format_size(file_size));
+
+    let progress = Arc::new(AtomicU64::new(resume_offset));
+    let start_time = Instant::now();
+    let first_pending = completed.iter().position(|&done| !done);
+
+    std::thread::scope(|scope| -> Result<(), FileTransferError> {
+        let mut handles = Vec::with_capacity(streams.len());
+        for (index, ((stream, channel), range)) in
+            streams.iter_mut().zip(channels.iter_mut()).zip(ranges).enumerate()
+        {
+            if completed[index] {
+                continue;
+            }
+            let progress = TransferProgress {
+                counter: Arc::clone(&progress),
+                whole_file_size: file_size,
+                start_time,
+                is_primary: Some(index) == first_pending,
+            };
+            handles.push(scope.spawn(move || send_range(stream, channel, path, range, &progress)));
+        }
+
+        for handle in handles {
+            handle.join().map_err(|_| FileTransferError::TransferError)??;
+        }
+        Ok(())
+    })?;
+
+    let transferred = progress.load(Ordering::Relaxed);
     if transferred != file_size {
         return Err(FileTransferError::IncompleteTransfer(transferred, file_size));
     }
 
+    let digest = hash_file(path)?;
+    channels[0].seal_and_send(&mut streams[0], digest.as_bytes())?;
+
     println!("\nTransfer complete! Total time: {:.1}s", start_time.elapsed().as_secs_f64());
     Ok(())
 }
 
-fn receive_and_save_file(stream: &mut TcpStream, filename: &str) -> Result<(), FileTransferError> {
-    if Path::new(filename).exists() {
-        return Err(FileTransferError::FileExists);
-    }
+/// Streams `range` of `path` over one connection: a disk-reader thread feeds a
+/// bounded `ByteChannel` while this thread drains it and seals each chunk onto
+/// the wire, so a fast disk never gets more than a fixed window ahead of a slow
+/// socket.
+fn send_range(
+    stream: &mut TcpStream,
+    channel: &mut SecureChannel,
+    path: &Path,
+    range: Range<u64>,
+    progress: &TransferProgress,
+) -> Result<(), FileTransferError> {
+    let total = range.end - range.start;
+    let prefetch = ByteChannel::new(BUFFER_SIZE * STREAM_WINDOW_CHUNKS);
+
+    let reader_path = path.to_path_buf();
+    let reader_channel = Arc::clone(&prefetch);
+    let reader = std::thread::spawn(move || -> io::Result<()> {
+        read_range_into(&reader_path, range, &reader_channel)
+    });
+
+    let send_result = (|| -> Result<u64, FileTransferError> {
+        let mut sent = 0u64;
+        let mut last_update = progress.start_time;
+        while sent < total {
+            let chunk = prefetch.read(BUFFER_SIZE);
+            if chunk.is_empty() {
+                break;
+            }
 
-    configure_tcp_stream(stream)?;
+            channel.seal_and_send(stream, blake3::hash(&chunk).as_bytes())?;
+            channel.seal_and_send(stream, &chunk)?;
+            sent += chunk.len() as u64;
+            progress.record(chunk.len() as u64, &mut last_update);
+        }
+        Ok(sent)
+    })();
+
+    // Close before joining regardless of how the loop above exited: the reader
+    // may be blocked mid-`write` on a full channel with nobody left to drain
+    // it, and `close` is what unblocks that wait.
+    prefetch.close();
+    reader.join().map_err(|_| FileTransferError::TransferError)??;
+
+    let sent = send_result?;
+    if sent != total {
+        return Err(FileTransferError::IncompleteTransfer(sent, total));
+    }
+    Ok(())
+}
 
-    let mut size_bytes = [0u8; 8];
-    stream.read_exact(&mut size_bytes)?;
-    let file_size = u64::from_le_bytes(size_bytes);
+fn read_range_into(path: &Path, range: Range<u64>, channel: &ByteChannel) -> io::Result<()> {
+    // Closed on every exit path, not just success, so a read error here can
+    // never leave the consumer in `send_range` blocked on the channel forever.
+    let result = (|| {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(range.start))?;
+
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let mut remaining = range.end - range.start;
+        while remaining > 0 {
+            let to_read = remaining.min(BUFFER_SIZE as u64) as usize;
+            file.read_exact(&mut buffer[..to_read])?;
+            channel.write(&buffer[..to_read]);
+            remaining -= to_read as u64;
+        }
+        Ok(())
+    })();
+    channel.close();
+    result
+}
 
-    let mut file = File::create(filename)?;
-    let mut buffer = vec![0; BUFFER_SIZE];
-    let mut received = 0;
-    let start_time = std::time::Instant::now();
-    let mut last_update = start_time;
+fn receive_and_save_file(
+    streams: &mut [TcpStream],
+    channels: &mut [SecureChannel],
+    dest: &Path,
+) -> Result<(), FileTransferError> {
+    for stream in streams.iter() {
+        configure_tcp_stream(stream)?;
+    }
 
-    println!("Receiving file: {} ({})", filename, format_size(file_size));
+    // No sidecar means there's nothing to resume: a pre-existing destination
+    // must be someone else's file, not a stalled transfer of this one, so
+    // refuse to clobber it instead of silently overwriting on open below.
+    // Decided before the per-file handshake read below so a rejection here
+    // drops the connection immediately instead of leaving the sender blocked
+    // on an ack that will never come.
+    if !progress_sidecar_path(dest).exists() && dest.exists() {
+        return Err(FileTransferError::FileExists);
+    }
 
-    while received < file_size {
-        let n = stream.read(&mut buffer)?;
-        if n == 0 { break; }
-        file.write_all(&buffer[..n])?;
-        received += n as u64;
+    // Which partitions a *previous* attempt already received and verified, per
+    // the progress sidecar -- never inferred from the destination file's
+    // length, since it's about to be preallocated to `file_size` regardless of
+    // how much data has actually landed.
+    let completed = load_completed_partitions(dest, streams.len());
+    // Persist the sidecar now, before a single byte has landed: otherwise a
+    // drop before the first partition verifies leaves no sidecar behind, and
+    // the guard above would mistake our own preallocated file for someone
+    // else's on the next attempt. Cleared only once the whole session (every
+    // file, not just this one) finishes, by `receive_session`.
+    save_completed_partitions(dest, &completed)?;
+
+    let size_bytes = channels[0].recv_and_open(&mut streams[0], 8)?;
+    let file_size = u64::from_le_bytes(size_bytes.try_into().unwrap());
+    let ranges = split_ranges(0, file_size, streams.len());
+    let already_verified: u64 = completed
+        .iter()
+        .zip(&ranges)
+        .filter(|(&done, _)| done)
+        .map(|(_, range)| range.end - range.start)
+        .sum();
+
+    let file = OpenOptions::new().create(true).write(true).open(dest)?;
+    file.set_len(file_size)?;
+    let file = Arc::new(Mutex::new(file));
+
+    let ack: Vec<u8> = completed.iter().map(|&done| done as u8).collect();
+    channels[0].seal_and_send(&mut streams[0], &ack)?;
+
+    if already_verified > 0 {
+        println!(
+            "Resuming {} ({} of {} already verified)",
+            dest.display(),
+            format_size(already_verified),
+            format_size(file_size)
+        );
+    }
+    println!("Receiving {} ({})", dest.display(), format_size(file_size));
+
+    let progress = Arc::new(AtomicU64::new(already_verified));
+    let start_time = Instant::now();
+    let first_pending = completed.iter().position(|&done| !done);
+    let completed = Mutex::new(completed);
+
+    std::thread::scope(|scope| -> Result<(), FileTransferError> {
+        let mut handles = Vec::with_capacity(streams.len());
+        for (index, ((stream, channel), range)) in
+            streams.iter_mut().zip(channels.iter_mut()).zip(ranges).enumerate()
+        {
+            if completed.lock().unwrap()[index] {
+                continue;
+            }
+            let file = Arc::clone(&file);
+            let completed = &completed;
+            let progress = TransferProgress {
+                counter: Arc::clone(&progress),
+                whole_file_size: file_size,
+                start_time,
+                is_primary: Some(index) == first_pending,
+            };
+            handles.push(scope.spawn(move || -> Result<(), FileTransferError> {
+                recv_range(stream, channel, &file, range, &progress)?;
+                mark_partition_complete(dest, completed, index)?;
+                Ok(())
+            }));
+        }
 
-        let now = std::time::Instant::now();
-        if now.duration_since(last_update).as_millis() >= 100 {
-            print_progress(received, file_size, start_time);
-            last_update = now;
+        for handle in handles {
+            handle.join().map_err(|_| FileTransferError::TransferError)??;
         }
-    }
+        Ok(())
+    })?;
 
+    let received = progress.load(Ordering::Relaxed);
     if received != file_size {
         return Err(FileTransferError::IncompleteTransfer(received, file_size));
     }
 
+    let expected_digest = channels[0].recv_and_open(&mut streams[0], 32)?;
+    let actual_digest = hash_file(dest)?;
+    if actual_digest.as_bytes().as_slice() != expected_digest.as_slice() {
+        // Don't trust any of this attempt's partitions against a future retry:
+        // something landed wrong despite every chunk's BLAKE3 checking out.
+        clear_progress_sidecar(dest);
+        return Err(FileTransferError::TransferError);
+    }
+
+    // Sidecar is left in place here, even though this file is done: if an
+    // earlier file in the same manifest still needs a reconnect to finish,
+    // `receive_session` re-enters this function for every file from entry 0,
+    // and an already-cleared sidecar on a finished file would make its
+    // existing destination look like someone else's file to the guard above.
+    // `receive_session` clears every file's sidecar once the whole session
+    // (not just this file) succeeds.
     println!("\nTransfer complete! Total time: {:.1}s", start_time.elapsed().as_secs_f64());
     Ok(())
 }
 
+/// Receives `range` of the destination file over one connection, verifying
+/// each chunk's BLAKE3 hash before writing it at the right offset.
+fn recv_range(
+    stream: &mut TcpStream,
+    channel: &mut SecureChannel,
+    file: &Arc<Mutex<File>>,
+    range: Range<u64>,
+    progress: &TransferProgress,
+) -> Result<(), FileTransferError> {
+    let total = range.end - range.start;
+    let mut received = 0u64;
+    let mut offset = range.start;
+    let mut last_update = progress.start_time;
+
+    while received < total {
+        let remaining = total - received;
+        let chunk_len = remaining.min(BUFFER_SIZE as u64) as usize;
+
+        let expected_hash = channel.recv_and_open(stream, 32)?;
+        let chunk = channel.recv_and_open(stream, chunk_len)?;
+        if blake3::hash(&chunk).as_bytes().as_slice() != expected_hash.as_slice() {
+            return Err(FileTransferError::TransferError);
+        }
+
+        {
+            let mut file = file.lock().unwrap();
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(&chunk)?;
+        }
+
+        offset += chunk.len() as u64;
+        received += chunk.len() as u64;
+        progress.record(chunk.len() as u64, &mut last_update);
+    }
+
+    if received != total {
+        return Err(FileTransferError::IncompleteTransfer(received, total));
+    }
+    Ok(())
+}
+
+/// Hashes a file on disk sequentially; used for the whole-file BLAKE3 check
+/// once all of a file's parallel ranges have landed.
+fn hash_file(path: &Path) -> io::Result<blake3::Hash> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher.finalize())
+}
+
 fn configure_tcp_stream(stream: &TcpStream) -> io::Result<()> {
     stream.set_nodelay(true)?;
     stream.set_read_timeout(Some(Duration::from_secs(600)))?;
@@ -266,7 +879,7 @@ fn configure_tcp_stream(stream: &TcpStream) -> io::Result<()> {
     Ok(())
 }
 
-fn print_progress(current: u64, total: u64, start_time: std::time::Instant) {
+fn print_progress(current: u64, total: u64, start_time: Instant) {
     let elapsed = start_time.elapsed().as_secs_f64();
     let speed = current as f64 / elapsed;
     let remaining = (total - current) as f64 / speed;
@@ -292,4 +905,45 @@ fn format_size(bytes: u64) -> String {
     }
 
     format!("{:.2} {}", size, UNITS[unit_index])
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_ranges_divides_evenly() {
+        let ranges = split_ranges(0, 100, 4);
+        assert_eq!(ranges, vec![0..25, 25..50, 50..75, 75..100]);
+    }
+
+    #[test]
+    fn split_ranges_puts_the_remainder_on_the_leading_partitions() {
+        let ranges = split_ranges(0, 10, 3);
+        assert_eq!(ranges, vec![0..4, 4..7, 7..10]);
+    }
+
+    #[test]
+    fn split_ranges_covers_the_whole_span_contiguously() {
+        let ranges = split_ranges(0, 97, STREAM_COUNT);
+        assert_eq!(ranges.first().unwrap().start, 0);
+        assert_eq!(ranges.last().unwrap().end, 97);
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+    }
+
+    #[test]
+    fn split_ranges_gives_empty_ranges_for_a_file_smaller_than_stream_count() {
+        // A 2-byte file over 4 streams: the first two partitions get one byte
+        // each, the rest are empty ranges rather than out of bounds.
+        let ranges = split_ranges(0, 2, 4);
+        assert_eq!(ranges, vec![0..1, 1..2, 2..2, 2..2]);
+    }
+
+    #[test]
+    fn split_ranges_handles_an_empty_file() {
+        let ranges = split_ranges(0, 0, STREAM_COUNT);
+        assert!(ranges.iter().all(|r| r.start == r.end));
+    }
+}
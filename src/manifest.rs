@@ -0,0 +1,207 @@
+use crate::FileTransferError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+/// Whether a `ManifestEntry` carries file data or just records an empty
+/// directory that needs to be recreated with nothing inside it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+}
+
+/// A single file or empty directory within a transfer, relative to the root
+/// being sent. `size` is always 0 for `Directory` entries.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub relative_path: String,
+    pub kind: EntryKind,
+    pub size: u64,
+    pub mode: u32,
+}
+
+/// The full list of files a sender intends to transfer, sent once up front so
+/// the receiver can recreate the directory tree before any file data arrives.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Builds a manifest for `root`: a recursive directory walk if `root` is a
+    /// directory, or a single entry if it's a plain file.
+    pub fn build(root: &Path) -> Result<Self, FileTransferError> {
+        let mut entries = Vec::new();
+
+        if root.is_dir() {
+            walk(root, root, &mut entries)?;
+        } else {
+            let metadata = fs::metadata(root)?;
+            let relative_path = root
+                .file_name()
+                .ok_or(FileTransferError::FileNotFound)?
+                .to_string_lossy()
+                .to_string();
+            entries.push(ManifestEntry {
+                relative_path,
+                kind: EntryKind::File,
+                size: metadata.len(),
+                mode: file_mode(&metadata),
+            });
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FileTransferError> {
+        serde_json::to_vec(self).map_err(|_| FileTransferError::TransferError)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FileTransferError> {
+        serde_json::from_slice(bytes).map_err(|_| FileTransferError::TransferError)
+    }
+}
+
+fn walk(root: &Path, dir: &Path, entries: &mut Vec<ManifestEntry>) -> Result<(), FileTransferError> {
+    let mut children: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    children.sort_by_key(|entry| entry.path());
+
+    for child in children {
+        let path = child.path();
+        let metadata = child.metadata()?;
+
+        if metadata.is_dir() {
+            if fs::read_dir(&path)?.next().is_none() {
+                // An empty directory has no file beneath it whose transfer would
+                // otherwise recreate it, so it needs its own manifest entry.
+                let relative_path = path
+                    .strip_prefix(root)
+                    .map_err(|_| FileTransferError::TransferError)?
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                entries.push(ManifestEntry {
+                    relative_path,
+                    kind: EntryKind::Directory,
+                    size: 0,
+                    mode: file_mode(&metadata),
+                });
+            } else {
+                walk(root, &path, entries)?;
+            }
+        } else if metadata.is_file() {
+            let relative_path = path
+                .strip_prefix(root)
+                .map_err(|_| FileTransferError::TransferError)?
+                .to_string_lossy()
+                .replace('\\', "/");
+            entries.push(ManifestEntry {
+                relative_path,
+                kind: EntryKind::File,
+                size: metadata.len(),
+                mode: file_mode(&metadata),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    metadata.permissions().mode()
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &fs::Metadata) -> u32 {
+    0o644
+}
+
+/// Applies a mode collected by `file_mode` to a received path. A no-op on
+/// non-unix targets, where `mode` is a placeholder rather than a real value.
+#[cfg(unix)]
+pub fn apply_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+pub fn apply_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Resolves `relative_path` against `dest_root`, rejecting absolute paths and
+/// `..` components so a malicious sender can't write outside the destination.
+pub fn sanitize_destination(dest_root: &Path, relative_path: &str) -> Result<PathBuf, FileTransferError> {
+    let candidate = Path::new(relative_path);
+
+    if candidate.is_absolute() {
+        return Err(FileTransferError::InvalidPath);
+    }
+
+    let mut sanitized = PathBuf::new();
+    for component in candidate.components() {
+        match component {
+            Component::Normal(part) => sanitized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(FileTransferError::InvalidPath)
+            }
+        }
+    }
+
+    if sanitized.as_os_str().is_empty() {
+        return Err(FileTransferError::InvalidPath);
+    }
+
+    Ok(dest_root.join(sanitized))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_destination_joins_normal_relative_paths() {
+        let dest = sanitize_destination(Path::new("/dest"), "a/b/c.txt").unwrap();
+        assert_eq!(dest, Path::new("/dest/a/b/c.txt"));
+    }
+
+    #[test]
+    fn sanitize_destination_strips_leading_cur_dir() {
+        let dest = sanitize_destination(Path::new("/dest"), "./a/b").unwrap();
+        assert_eq!(dest, Path::new("/dest/a/b"));
+    }
+
+    #[test]
+    fn sanitize_destination_rejects_absolute_paths() {
+        let err = sanitize_destination(Path::new("/dest"), "/etc/passwd").unwrap_err();
+        assert!(matches!(err, FileTransferError::InvalidPath));
+    }
+
+    #[test]
+    fn sanitize_destination_rejects_parent_dir_traversal() {
+        let err = sanitize_destination(Path::new("/dest"), "../../etc/passwd").unwrap_err();
+        assert!(matches!(err, FileTransferError::InvalidPath));
+    }
+
+    #[test]
+    fn sanitize_destination_rejects_embedded_parent_dir() {
+        let err = sanitize_destination(Path::new("/dest"), "a/../../etc/passwd").unwrap_err();
+        assert!(matches!(err, FileTransferError::InvalidPath));
+    }
+
+    #[test]
+    fn sanitize_destination_rejects_empty_and_cur_dir_only_paths() {
+        assert!(matches!(
+            sanitize_destination(Path::new("/dest"), "").unwrap_err(),
+            FileTransferError::InvalidPath
+        ));
+        assert!(matches!(
+            sanitize_destination(Path::new("/dest"), "./").unwrap_err(),
+            FileTransferError::InvalidPath
+        ));
+    }
+}
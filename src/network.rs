@@ -1,20 +1,44 @@
-use std::sync::Arc;  // Add this import
+use crate::config::Config;
 use crate::error::SlkrdError;
+use crate::signaling::{SignalingClient, SignalingMessageType};
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
 use webrtc::api::APIBuilder;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
 use webrtc::data_channel::RTCDataChannel;
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use webrtc::ice_transport::ice_server::RTCIceServer;
 use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 use webrtc::peer_connection::RTCPeerConnection;
 
 pub struct NetworkManager {
     pub peer_connection: RTCPeerConnection,
     pub data_channel: Option<Arc<RTCDataChannel>>,
+    /// Receives every frame the data channel delivers. Populated by
+    /// `connect_as_answerer`, which wires the sink in before the channel is
+    /// reported open so no early chunk from the offerer can be missed.
+    pub incoming: Option<UnboundedReceiver<Bytes>>,
 }
 
 impl NetworkManager {
-    pub async fn new(config: &crate::config::Config) -> Result<Self, SlkrdError> {
-        let mut webrtc_config = RTCConfiguration::default();
-        // Add STUN/TURN servers from config
-        
+    pub async fn new(config: &Config) -> Result<Self, SlkrdError> {
+        let ice_servers = config
+            .stun_servers
+            .iter()
+            .chain(config.turn_servers.iter())
+            .map(|url| RTCIceServer {
+                urls: vec![url.clone()],
+                ..Default::default()
+            })
+            .collect();
+        let webrtc_config = RTCConfiguration {
+            ice_servers,
+            ..Default::default()
+        };
+
         let api = APIBuilder::new().build();
         let peer_connection = api
             .new_peer_connection(webrtc_config)
@@ -24,6 +48,7 @@ impl NetworkManager {
         Ok(Self {
             peer_connection,
             data_channel: None,
+            incoming: None,
         })
     }
 
@@ -33,8 +58,177 @@ impl NetworkManager {
             .create_data_channel(label, None)
             .await
             .map_err(SlkrdError::WebRTC)?;
-        
+
         self.data_channel = Some(data_channel);
         Ok(())
     }
+
+    /// Drives the offerer side of the SDP/ICE exchange: creates a data channel and
+    /// offer, sends it over `signaling`, then applies the answer and any ICE
+    /// candidates relayed back until the data channel is open.
+    pub async fn connect_as_offerer(
+        &mut self,
+        signaling: &SignalingClient,
+        label: &str,
+    ) -> Result<(), SlkrdError> {
+        self.create_data_channel(label).await?;
+        self.forward_local_candidates(signaling);
+
+        let offer = self
+            .peer_connection
+            .create_offer(None)
+            .await
+            .map_err(SlkrdError::WebRTC)?;
+        self.peer_connection
+            .set_local_description(offer.clone())
+            .await
+            .map_err(SlkrdError::WebRTC)?;
+        signaling
+            .send(SignalingMessageType::Offer, offer.sdp)
+            .await?;
+
+        loop {
+            let message = signaling.recv().await?;
+            match message.message_type {
+                SignalingMessageType::Answer => {
+                    let answer = RTCSessionDescription::answer(message.payload)
+                        .map_err(SlkrdError::WebRTC)?;
+                    self.peer_connection
+                        .set_remote_description(answer)
+                        .await
+                        .map_err(SlkrdError::WebRTC)?;
+                    break;
+                }
+                SignalingMessageType::IceCandidate => self.add_remote_candidate(&message.payload).await?,
+                SignalingMessageType::Offer | SignalingMessageType::Hello => {}
+            }
+        }
+
+        self.wait_for_data_channel_open(signaling).await
+    }
+
+    /// Drives the answerer side: registers with the relay, waits for the offer,
+    /// answers it, and applies any ICE candidates relayed back until the data
+    /// channel is open.
+    pub async fn connect_as_answerer(&mut self, signaling: &SignalingClient) -> Result<(), SlkrdError> {
+        let (data_channel_tx, mut data_channel_rx) = tokio::sync::mpsc::unbounded_channel();
+        self.peer_connection.on_data_channel(Box::new(move |dc| {
+            let _ = data_channel_tx.send(dc);
+            Box::pin(async {})
+        }));
+        self.forward_local_candidates(signaling);
+
+        // The relay only learns this peer's address from a message it sends, and
+        // the offer/any early ICE candidates are buffered there until it does —
+        // so register before waiting on anything.
+        signaling.send(SignalingMessageType::Hello, String::new()).await?;
+
+        loop {
+            let message = signaling.recv().await?;
+            match message.message_type {
+                SignalingMessageType::Offer => {
+                    let offer = RTCSessionDescription::offer(message.payload)
+                        .map_err(SlkrdError::WebRTC)?;
+                    self.peer_connection
+                        .set_remote_description(offer)
+                        .await
+                        .map_err(SlkrdError::WebRTC)?;
+
+                    let answer = self
+                        .peer_connection
+                        .create_answer(None)
+                        .await
+                        .map_err(SlkrdError::WebRTC)?;
+                    self.peer_connection
+                        .set_local_description(answer.clone())
+                        .await
+                        .map_err(SlkrdError::WebRTC)?;
+                    signaling
+                        .send(SignalingMessageType::Answer, answer.sdp)
+                        .await?;
+                    break;
+                }
+                SignalingMessageType::IceCandidate => self.add_remote_candidate(&message.payload).await?,
+                SignalingMessageType::Answer | SignalingMessageType::Hello => {}
+            }
+        }
+
+        let data_channel = data_channel_rx
+            .recv()
+            .await
+            .ok_or_else(|| SlkrdError::Network("peer closed before opening a data channel".into()))?;
+
+        // Wire the message sink in before we ever await on the channel being
+        // open: webrtc-rs can deliver frames as soon as the remote side sees
+        // the channel open, which may race this task's own open event.
+        let (chunk_tx, chunk_rx) = tokio::sync::mpsc::unbounded_channel();
+        data_channel.on_message(Box::new(move |message: DataChannelMessage| {
+            let _ = chunk_tx.send(message.data);
+            Box::pin(async {})
+        }));
+        self.incoming = Some(chunk_rx);
+        self.data_channel = Some(data_channel);
+
+        self.wait_for_data_channel_open(signaling).await
+    }
+
+    fn forward_local_candidates(&self, signaling: &SignalingClient) {
+        let signaling = signaling.clone();
+        self.peer_connection.on_ice_candidate(Box::new(move |candidate| {
+            let signaling = signaling.clone();
+            Box::pin(async move {
+                let Some(candidate) = candidate else { return };
+                if let Ok(init) = candidate.to_json() {
+                    if let Ok(payload) = serde_json::to_string(&init) {
+                        let _ = signaling.send(SignalingMessageType::IceCandidate, payload).await;
+                    }
+                }
+            })
+        }));
+    }
+
+    async fn add_remote_candidate(&self, payload: &str) -> Result<(), SlkrdError> {
+        let init: RTCIceCandidateInit =
+            serde_json::from_str(payload).map_err(|e| SlkrdError::Network(e.to_string()))?;
+        self.peer_connection
+            .add_ice_candidate(init)
+            .await
+            .map_err(SlkrdError::WebRTC)
+    }
+
+    /// Waits for the negotiated data channel to open, applying every ICE
+    /// candidate `signaling` relays in the meantime.
+    async fn wait_for_data_channel_open(&self, signaling: &SignalingClient) -> Result<(), SlkrdError> {
+        let data_channel = self
+            .data_channel
+            .as_ref()
+            .ok_or_else(|| SlkrdError::Network("no data channel negotiated".into()))?;
+
+        let (open_tx, open_rx) = tokio::sync::oneshot::channel();
+        let open_tx = std::sync::Mutex::new(Some(open_tx));
+        data_channel.on_open(Box::new(move || {
+            if let Some(tx) = open_tx.lock().unwrap().take() {
+                let _ = tx.send(());
+            }
+            Box::pin(async {})
+        }));
+
+        let opened = tokio::time::timeout(Duration::from_secs(30), open_rx);
+        tokio::pin!(opened);
+        loop {
+            tokio::select! {
+                result = &mut opened => {
+                    return result
+                        .map_err(|_| SlkrdError::Network("timed out waiting for data channel to open".into()))?
+                        .map_err(|_| SlkrdError::Network("data channel closed before opening".into()));
+                }
+                message = signaling.recv() => {
+                    let message = message?;
+                    if let SignalingMessageType::IceCandidate = message.message_type {
+                        self.add_remote_candidate(&message.payload).await?;
+                    }
+                }
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -2,10 +2,11 @@ use crate::error::SlkrdError;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::net::UdpSocket;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignalingMessage {
     pub session_id: Uuid,
     pub passcode: String,
@@ -13,8 +14,12 @@ pub struct SignalingMessage {
     pub payload: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SignalingMessageType {
+    /// Sent by the receiver as its first message, before it has an offer to
+    /// answer, purely so the relay learns `receiver_addr` in time to deliver
+    /// the sender's offer (and any ICE candidates trickled before the answer).
+    Hello,
     Offer,
     Answer,
     IceCandidate,
@@ -25,11 +30,16 @@ pub struct SignalingServer {
     sessions: HashMap<String, SessionInfo>,
 }
 
-#[derive(Debug)]
+/// Per-passcode rendezvous state. Sender and receiver can each show up first,
+/// so both addresses start unknown and are filled in independently; anything
+/// that can't be delivered yet (the offer, trickled candidates) is buffered
+/// until the other side registers.
+#[derive(Debug, Default)]
 struct SessionInfo {
-    id: Uuid,
-    sender_addr: SocketAddr,
+    sender_addr: Option<SocketAddr>,
     receiver_addr: Option<SocketAddr>,
+    offer: Option<SignalingMessage>,
+    pending_candidates: Vec<(SocketAddr, SignalingMessage)>,
 }
 
 impl SignalingServer {
@@ -37,7 +47,7 @@ impl SignalingServer {
         let socket = UdpSocket::bind(bind_addr)
             .await
             .map_err(|e| SlkrdError::Network(e.to_string()))?;
-        
+
         Ok(Self {
             socket,
             sessions: HashMap::new(),
@@ -46,7 +56,7 @@ impl SignalingServer {
 
     pub async fn run(&mut self) -> Result<(), SlkrdError> {
         let mut buf = vec![0u8; 65536];
-        
+
         loop {
             let (len, addr) = self
                 .socket
@@ -66,49 +76,138 @@ impl SignalingServer {
         message: SignalingMessage,
         addr: SocketAddr,
     ) -> Result<(), SlkrdError> {
+        let passcode = message.passcode.clone();
+
         match message.message_type {
             SignalingMessageType::Offer => {
-                let session = SessionInfo {
-                    id: message.session_id,
-                    sender_addr: addr,
-                    receiver_addr: None,
-                };
-                self.sessions.insert(message.passcode.clone(), session);
+                let session = self.sessions.entry(passcode.clone()).or_default();
+                session.sender_addr = Some(addr);
+                let receiver_addr = session.receiver_addr;
+                session.offer = Some(message.clone());
+
+                if let Some(receiver_addr) = receiver_addr {
+                    self.forward(&message, receiver_addr).await?;
+                }
+            }
+            SignalingMessageType::Hello => {
+                let session = self.sessions.entry(passcode.clone()).or_default();
+                session.receiver_addr = Some(addr);
+                if let Some(offer) = session.offer.clone() {
+                    self.forward(&offer, addr).await?;
+                }
             }
             SignalingMessageType::Answer => {
-                if let Some(session) = self.sessions.get_mut(&message.passcode) {
+                let sender_addr = self.sessions.get_mut(&passcode).and_then(|session| {
                     session.receiver_addr = Some(addr);
-                    // Forward answer to sender
-                    if let Err(e) = self
-                        .socket
-                        .send_to(&serde_json::to_vec(&message).unwrap(), session.sender_addr)
-                        .await
-                    {
-                        return Err(SlkrdError::Network(e.to_string()));
-                    }
+                    session.sender_addr
+                });
+                if let Some(sender_addr) = sender_addr {
+                    self.forward(&message, sender_addr).await?;
                 }
             }
             SignalingMessageType::IceCandidate => {
-                if let Some(session) = self.sessions.get(&message.passcode) {
-                    // Forward ICE candidate to the other peer
-                    let target_addr = if addr == session.sender_addr {
-                        session.receiver_addr
-                    } else {
-                        Some(session.sender_addr)
-                    };
-
-                    if let Some(target) = target_addr {
-                        if let Err(e) = self
-                            .socket
-                            .send_to(&serde_json::to_vec(&message).unwrap(), target)
-                            .await
-                        {
-                            return Err(SlkrdError::Network(e.to_string()));
-                        }
-                    }
+                let session = self.sessions.entry(passcode.clone()).or_default();
+                let target = peer_addr_of(session, addr);
+
+                match target {
+                    Some(target) => self.forward(&message, target).await?,
+                    None => session.pending_candidates.push((addr, message.clone())),
                 }
             }
         }
+
+        self.flush_pending_candidates(&passcode).await
+    }
+
+    async fn forward(&self, message: &SignalingMessage, target: SocketAddr) -> Result<(), SlkrdError> {
+        self.socket
+            .send_to(&serde_json::to_vec(message).unwrap(), target)
+            .await
+            .map_err(|e| SlkrdError::Network(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Re-delivers any ICE candidates that arrived before both peer addresses
+    /// of `passcode`'s session were known, now that one more has just been set.
+    async fn flush_pending_candidates(&mut self, passcode: &str) -> Result<(), SlkrdError> {
+        let Some(session) = self.sessions.get_mut(passcode) else { return Ok(()) };
+        if session.pending_candidates.is_empty() {
+            return Ok(());
+        }
+        let pending = std::mem::take(&mut session.pending_candidates);
+
+        let mut still_pending = Vec::new();
+        for (origin, message) in pending {
+            match self.sessions.get(passcode).and_then(|session| peer_addr_of(session, origin)) {
+                Some(target) => self.forward(&message, target).await?,
+                None => still_pending.push((origin, message)),
+            }
+        }
+
+        if let Some(session) = self.sessions.get_mut(passcode) {
+            session.pending_candidates = still_pending;
+        }
         Ok(())
     }
+}
+
+/// The other peer's address for a candidate that arrived from `origin`, if known yet.
+fn peer_addr_of(session: &SessionInfo, origin: SocketAddr) -> Option<SocketAddr> {
+    if Some(origin) == session.sender_addr {
+        session.receiver_addr
+    } else if Some(origin) == session.receiver_addr {
+        session.sender_addr
+    } else {
+        None
+    }
+}
+
+/// A peer's handle to a running `SignalingServer`: sends this peer's
+/// Offer/Answer/IceCandidate messages to it and receives the other side's in
+/// return, all keyed by `passcode` so the server can pair the two sockets.
+#[derive(Clone)]
+pub struct SignalingClient {
+    socket: Arc<UdpSocket>,
+    server_addr: SocketAddr,
+    session_id: Uuid,
+    passcode: String,
+}
+
+impl SignalingClient {
+    pub async fn connect(server_addr: SocketAddr, passcode: &str) -> Result<Self, SlkrdError> {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .await
+            .map_err(|e| SlkrdError::Network(e.to_string()))?;
+        Ok(Self {
+            socket: Arc::new(socket),
+            server_addr,
+            session_id: Uuid::new_v4(),
+            passcode: passcode.to_string(),
+        })
+    }
+
+    pub async fn send(&self, message_type: SignalingMessageType, payload: String) -> Result<(), SlkrdError> {
+        let message = SignalingMessage {
+            session_id: self.session_id,
+            passcode: self.passcode.clone(),
+            message_type,
+            payload,
+        };
+        let bytes = serde_json::to_vec(&message).map_err(|e| SlkrdError::Network(e.to_string()))?;
+        self.socket
+            .send_to(&bytes, self.server_addr)
+            .await
+            .map_err(|e| SlkrdError::Network(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn recv(&self) -> Result<SignalingMessage, SlkrdError> {
+        let mut buf = vec![0u8; 65536];
+        let (len, _) = self
+            .socket
+            .recv_from(&mut buf)
+            .await
+            .map_err(|e| SlkrdError::Network(e.to_string()))?;
+        serde_json::from_slice(&buf[..len]).map_err(|e| SlkrdError::Network(e.to_string()))
+    }
 }
\ No newline at end of file
@@ -1,9 +1,16 @@
+use crate::config::Config;
 use crate::error::SlkrdError;
 use crate::file::FileManager;
 use crate::network::NetworkManager;
+use crate::signaling::SignalingClient;
+use bytes::{Bytes, BytesMut};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::path::PathBuf;
 
+/// Sentinel frame that closes out a transfer: the data channel carries no
+/// separate "end of file" message type, so an empty frame marks EOF.
+const EOF_MARKER: &[u8] = &[];
+
 pub struct Transfer {
     network: NetworkManager,
     file_manager: FileManager,
@@ -15,8 +22,9 @@ impl Transfer {
         path: PathBuf,
         chunk_size: usize,
         total_size: u64,
+        config: &Config,
     ) -> Result<Self, SlkrdError> {
-        let network = NetworkManager::new(&Default::default()).await?;
+        let network = NetworkManager::new(config).await?;
         let file_manager = FileManager::new_reader(path, chunk_size).await?;
         let progress = create_progress_bar(total_size);
 
@@ -31,8 +39,9 @@ impl Transfer {
         path: PathBuf,
         chunk_size: usize,
         total_size: u64,
+        config: &Config,
     ) -> Result<Self, SlkrdError> {
-        let network = NetworkManager::new(&Default::default()).await?;
+        let network = NetworkManager::new(config).await?;
         let file_manager = FileManager::new_writer(path, chunk_size).await?;
         let progress = create_progress_bar(total_size);
 
@@ -42,9 +51,84 @@ impl Transfer {
             progress,
         })
     }
+
+    /// Completes the offerer side of the SDP/ICE exchange over `signaling`, then
+    /// reads the file in `chunk_size` pieces and pushes each over the data
+    /// channel until EOF, signaled by a trailing empty frame.
+    pub async fn run_sender(&mut self, signaling: &SignalingClient) -> Result<(), SlkrdError> {
+        self.network.connect_as_offerer(signaling, "slkrd").await?;
+        let data_channel = self
+            .network
+            .data_channel
+            .clone()
+            .ok_or_else(|| SlkrdError::Network("no data channel negotiated".into()))?;
+
+        while let Some(chunk) = self.file_manager.read_chunk().await? {
+            let len = chunk.len() as u64;
+            data_channel
+                .send(&chunk.freeze())
+                .await
+                .map_err(SlkrdError::WebRTC)?;
+            self.progress.inc(len);
+        }
+
+        data_channel
+            .send(&Bytes::from_static(EOF_MARKER))
+            .await
+            .map_err(SlkrdError::WebRTC)?;
+        self.progress.finish_with_message("transfer complete");
+        Ok(())
+    }
+
+    /// Completes the answerer side of the SDP/ICE exchange over `signaling`,
+    /// then writes every incoming data channel frame to disk until the sender's
+    /// trailing empty frame marks EOF. If the channel closes before that
+    /// marker arrives, the transfer is incomplete and is reported as such
+    /// rather than as a silent success.
+    pub async fn run_receiver(&mut self, signaling: &SignalingClient) -> Result<(), SlkrdError> {
+        self.network.connect_as_answerer(signaling).await?;
+        let mut chunk_rx = self
+            .network
+            .incoming
+            .take()
+            .ok_or_else(|| SlkrdError::Network("no data channel negotiated".into()))?;
+
+        let mut eof = false;
+        while let Some(chunk) = chunk_rx.recv().await {
+            if chunk.is_empty() {
+                eof = true;
+                break;
+            }
+            let len = chunk.len() as u64;
+            self.file_manager.write_chunk(BytesMut::from(chunk.as_ref())).await?;
+            self.progress.inc(len);
+        }
+
+        if !eof {
+            return Err(SlkrdError::TransferFailed(
+                "data channel closed before end-of-file marker; transfer is incomplete".into(),
+            ));
+        }
+
+        self.progress.finish_with_message("transfer complete");
+        Ok(())
+    }
 }
 
+/// `total_size` of 0 means the receiver doesn't know the file's size up front
+/// (the WebRTC path negotiates no manifest), so it gets a spinner instead of a
+/// bar with a nonsensical total.
 fn create_progress_bar(total_size: u64) -> ProgressBar {
+    if total_size == 0 {
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("[{elapsed_precise}] {spinner} {bytes} received")
+                .unwrap(),
+        );
+        return pb;
+    }
+
     let pb = ProgressBar::new(total_size);
     pb.set_style(
         ProgressStyle::default_bar()